@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+
+// https://www.w3.org/TR/rdf-canon/ (URDNA2015)
+//
+// A single RDF quad as produced by `to_rdf`, with each component already
+// serialized in N-Quads term syntax (e.g. `<http://example.org/s>`, `_:b0`,
+// `"literal"@en`), so the canonicalization algorithm only has to juggle
+// strings rather than a typed RDF term model.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Quad {
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+    pub graph: Option<String>,
+}
+
+impl Quad {
+    fn to_nquad(&self) -> String {
+        match &self.graph {
+            Some(graph) => format!(
+                "{} {} {} {} .",
+                self.subject, self.predicate, self.object, graph
+            ),
+            None => format!("{} {} {} .", self.subject, self.predicate, self.object),
+        }
+    }
+}
+
+fn is_blank_node(term: &str) -> bool {
+    term.starts_with("_:")
+}
+
+fn sha256_hex(input: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(input.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Hands out canonical (`_:c14n0`, `_:c14n1`, ...) or temporary (`_:b0`,
+// `_:b1`, ...) blank node identifiers, keyed by the node's original label so
+// the same input always maps to the same output.
+#[derive(Clone)]
+struct IdentifierIssuer {
+    prefix: String,
+    counter: usize,
+    issued: HashMap<String, String>,
+    order: Vec<String>,
+}
+
+impl IdentifierIssuer {
+    fn new(prefix: &str) -> Self {
+        IdentifierIssuer {
+            prefix: prefix.to_owned(),
+            counter: 0,
+            issued: HashMap::new(),
+            order: Vec::new(),
+        }
+    }
+
+    fn has(&self, id: &str) -> bool {
+        self.issued.contains_key(id)
+    }
+
+    fn get(&self, id: &str) -> Option<&String> {
+        self.issued.get(id)
+    }
+
+    fn issue(&mut self, id: &str) -> String {
+        if let Some(existing) = self.issued.get(id) {
+            return existing.clone();
+        }
+
+        let issued = format!("{}{}", self.prefix, self.counter);
+        self.counter += 1;
+        self.issued.insert(id.to_owned(), issued.clone());
+        self.order.push(id.to_owned());
+
+        issued
+    }
+
+    // original labels, in the order canonical identifiers were issued to them
+    fn ordered_originals(&self) -> &[String] {
+        &self.order
+    }
+}
+
+fn quads_by_blank_node(quads: &[Quad]) -> HashMap<String, Vec<Quad>> {
+    let mut map: HashMap<String, Vec<Quad>> = HashMap::new();
+
+    for quad in quads {
+        for component in [&quad.subject, &quad.object] {
+            if is_blank_node(component) {
+                map.entry(component.clone()).or_default().push(quad.clone());
+            }
+        }
+        if let Some(graph) = &quad.graph {
+            if is_blank_node(graph) {
+                map.entry(graph.clone()).or_default().push(quad.clone());
+            }
+        }
+    }
+
+    map
+}
+
+// Step 2: serialize `bnode`'s quads with itself replaced by `_:a` and every
+// other blank node replaced by `_:z`, then hash the sorted result. Two blank
+// nodes that play an identical role in the dataset hash identically.
+fn hash_first_degree_quads(bnode: &str, quads_by_bnode: &HashMap<String, Vec<Quad>>) -> String {
+    let mask = |term: &str| -> String {
+        if term == bnode {
+            "_:a".to_owned()
+        } else if is_blank_node(term) {
+            "_:z".to_owned()
+        } else {
+            term.to_owned()
+        }
+    };
+
+    let mut serialized: Vec<String> = quads_by_bnode[bnode]
+        .iter()
+        .map(|quad| {
+            Quad {
+                subject: mask(&quad.subject),
+                predicate: quad.predicate.clone(),
+                object: mask(&quad.object),
+                graph: quad.graph.as_ref().map(|g| mask(g)),
+            }
+            .to_nquad()
+        })
+        .collect();
+
+    serialized.sort();
+    sha256_hex(&serialized.concat())
+}
+
+// Hash a blank node related to `quad` through `position` ("s"/"o"/"g"),
+// using whatever identifier has already been assigned to it (canonical,
+// then temporary, falling back to its first-degree hash).
+fn hash_related_blank_node(
+    related: &str,
+    quad: &Quad,
+    quads_by_bnode: &HashMap<String, Vec<Quad>>,
+    canonical_issuer: &IdentifierIssuer,
+    issuer: &IdentifierIssuer,
+    position: &str,
+) -> String {
+    let identifier = canonical_issuer
+        .get(related)
+        .or_else(|| issuer.get(related))
+        .cloned()
+        .unwrap_or_else(|| hash_first_degree_quads(related, quads_by_bnode));
+
+    sha256_hex(&format!("{}{}{}", position, quad.predicate, identifier))
+}
+
+fn related_blank_nodes(identifier: &str, quad: &Quad) -> Vec<(String, &'static str)> {
+    let mut related = Vec::new();
+
+    if quad.subject != identifier && is_blank_node(&quad.subject) {
+        related.push((quad.subject.clone(), "s"));
+    }
+    if quad.object != identifier && is_blank_node(&quad.object) {
+        related.push((quad.object.clone(), "o"));
+    }
+    if let Some(graph) = &quad.graph {
+        if graph != identifier && is_blank_node(graph) {
+            related.push((graph.clone(), "g"));
+        }
+    }
+
+    related
+}
+
+fn permutations(mut items: Vec<String>) -> Vec<Vec<String>> {
+    if items.len() <= 1 {
+        return vec![items];
+    }
+
+    let mut result = Vec::new();
+    let n = items.len();
+    permute(&mut items, n, &mut result);
+    result
+}
+
+// Heap's algorithm
+fn permute(items: &mut Vec<String>, k: usize, result: &mut Vec<Vec<String>>) {
+    if k == 1 {
+        result.push(items.clone());
+        return;
+    }
+
+    for i in 0..k {
+        permute(items, k - 1, result);
+        if k % 2 == 0 {
+            items.swap(i, k - 1);
+        } else {
+            items.swap(0, k - 1);
+        }
+    }
+}
+
+// Step 4 (Hash N-Degree Quads): for blank nodes whose first-degree hash
+// isn't unique, recursively distinguish them by exploring how their related
+// blank nodes could be labeled, trying every permutation of nodes that
+// share a hash and keeping whichever assignment produces the
+// lexicographically smallest path.
+fn hash_n_degree_quads(
+    identifier: &str,
+    quads_by_bnode: &HashMap<String, Vec<Quad>>,
+    canonical_issuer: &IdentifierIssuer,
+    mut issuer: IdentifierIssuer,
+) -> (String, IdentifierIssuer) {
+    let mut hash_to_related: HashMap<String, Vec<String>> = HashMap::new();
+
+    for quad in &quads_by_bnode[identifier] {
+        for (related, position) in related_blank_nodes(identifier, quad) {
+            let hash = hash_related_blank_node(
+                &related,
+                quad,
+                quads_by_bnode,
+                canonical_issuer,
+                &issuer,
+                position,
+            );
+            hash_to_related.entry(hash).or_default().push(related);
+        }
+    }
+
+    let mut data_to_hash = String::new();
+    let mut sorted_hashes: Vec<String> = hash_to_related.keys().cloned().collect();
+    sorted_hashes.sort();
+
+    for hash in sorted_hashes {
+        data_to_hash.push_str(&hash);
+
+        let mut related = hash_to_related.remove(&hash).unwrap();
+        related.sort();
+        related.dedup();
+
+        let mut chosen_path: Option<String> = None;
+        let mut chosen_issuer = issuer.clone();
+
+        for permutation in permutations(related) {
+            let mut issuer_copy = issuer.clone();
+            let mut path = String::new();
+            let mut recursion_list = Vec::new();
+
+            for related_id in &permutation {
+                if let Some(canonical) = canonical_issuer.get(related_id) {
+                    path.push_str(canonical);
+                } else {
+                    if !issuer_copy.has(related_id) {
+                        recursion_list.push(related_id.clone());
+                    }
+                    path.push_str(&issuer_copy.issue(related_id));
+                }
+            }
+
+            for related_id in recursion_list {
+                let (related_hash, updated_issuer) = hash_n_degree_quads(
+                    &related_id,
+                    quads_by_bnode,
+                    canonical_issuer,
+                    issuer_copy,
+                );
+                issuer_copy = updated_issuer;
+                path.push_str(&issuer_copy.issue(&related_id));
+                path.push('<');
+                path.push_str(&related_hash);
+                path.push('>');
+            }
+
+            if chosen_path.as_ref().map_or(true, |best| &path < best) {
+                chosen_path = Some(path);
+                chosen_issuer = issuer_copy;
+            }
+        }
+
+        data_to_hash.push_str(&chosen_path.unwrap_or_default());
+        issuer = chosen_issuer;
+    }
+
+    (sha256_hex(&data_to_hash), issuer)
+}
+
+fn relabel(quad: &Quad, canonical_issuer: &IdentifierIssuer) -> Quad {
+    let relabel_term = |term: &str| -> String {
+        if is_blank_node(term) {
+            canonical_issuer
+                .get(term)
+                .cloned()
+                .unwrap_or_else(|| term.to_owned())
+        } else {
+            term.to_owned()
+        }
+    };
+
+    Quad {
+        subject: relabel_term(&quad.subject),
+        predicate: quad.predicate.clone(),
+        object: relabel_term(&quad.object),
+        graph: quad.graph.as_ref().map(|g| relabel_term(g)),
+    }
+}
+
+// https://www.w3.org/TR/rdf-canon/#canon-algorithm
+// Canonicalizes an RDF dataset (the output of `to_rdf`) into deterministic
+// N-Quads with stable `_:c14nN` blank node labels, as required by URDNA2015
+// before a dataset can be hashed and signed (e.g. Linked Data Signatures
+// over an ActivityPub object).
+pub fn canonicalize(quads: &[Quad]) -> String {
+    let quads_by_bnode = quads_by_blank_node(quads);
+
+    let mut hash_to_bnodes: HashMap<String, Vec<String>> = HashMap::new();
+    for bnode in quads_by_bnode.keys() {
+        let hash = hash_first_degree_quads(bnode, &quads_by_bnode);
+        hash_to_bnodes.entry(hash).or_default().push(bnode.clone());
+    }
+
+    let mut canonical_issuer = IdentifierIssuer::new("_:c14n");
+    let mut non_unique: Vec<Vec<String>> = Vec::new();
+
+    let mut sorted_hashes: Vec<String> = hash_to_bnodes.keys().cloned().collect();
+    sorted_hashes.sort();
+
+    for hash in sorted_hashes {
+        let bnodes = &hash_to_bnodes[&hash];
+        if bnodes.len() == 1 {
+            canonical_issuer.issue(&bnodes[0]);
+        } else {
+            non_unique.push(bnodes.clone());
+        }
+    }
+
+    for bnodes in non_unique {
+        let mut hash_path_list: Vec<(String, IdentifierIssuer)> = Vec::new();
+
+        for bnode in &bnodes {
+            if canonical_issuer.has(bnode) {
+                continue;
+            }
+
+            let mut temp_issuer = IdentifierIssuer::new("_:b");
+            temp_issuer.issue(bnode);
+
+            hash_path_list.push(hash_n_degree_quads(
+                bnode,
+                &quads_by_bnode,
+                &canonical_issuer,
+                temp_issuer,
+            ));
+        }
+
+        hash_path_list.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (_, issuer) in hash_path_list {
+            for original in issuer.ordered_originals() {
+                if !canonical_issuer.has(original) {
+                    canonical_issuer.issue(original);
+                }
+            }
+        }
+    }
+
+    let mut serialized: Vec<String> = quads
+        .iter()
+        .map(|quad| relabel(quad, &canonical_issuer).to_nquad())
+        .collect();
+    serialized.sort();
+
+    if serialized.is_empty() {
+        String::new()
+    } else {
+        serialized.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(s: &str, p: &str, o: &str) -> Quad {
+        Quad {
+            subject: s.to_owned(),
+            predicate: p.to_owned(),
+            object: o.to_owned(),
+            graph: None,
+        }
+    }
+
+    // Two blank nodes related only to each other serialize identically under
+    // `hash_first_degree_quads` (both produce the masked pair `_:a <p> _:z .`
+    // / `_:z <p> _:a .`), so telling them apart exercises
+    // `hash_n_degree_quads`. The canonical output must not depend on the
+    // original blank node labels.
+    #[test]
+    fn symmetric_pair_is_stable_regardless_of_input_labels() {
+        let a = vec![
+            quad("_:b0", "<http://example.org/p>", "_:b1"),
+            quad("_:b1", "<http://example.org/p>", "_:b0"),
+        ];
+        let b = vec![
+            quad("_:x", "<http://example.org/p>", "_:y"),
+            quad("_:y", "<http://example.org/p>", "_:x"),
+        ];
+
+        let expected = "_:c14n0 <http://example.org/p> _:c14n1 .\n\
+                         _:c14n1 <http://example.org/p> _:c14n0 .\n";
+
+        assert_eq!(canonicalize(&a), expected);
+        assert_eq!(canonicalize(&b), expected);
+    }
+
+    // `_:b1` and `_:b2` are both "the other object of `_:b0`'s `<p>` edge",
+    // but each node's own `<q>` quad is part of its first-degree hash, so all
+    // three blank nodes hash uniquely and are labeled directly in hash order
+    // without `hash_n_degree_quads`. A regression that conflates the two
+    // objects would collide their labels.
+    #[test]
+    fn distinct_first_degree_hashes_despite_duplicate_property() {
+        let quads = vec![
+            quad("_:b0", "<http://example.org/p>", "_:b1"),
+            quad("_:b0", "<http://example.org/p>", "_:b2"),
+            quad("_:b1", "<http://example.org/q>", "\"1\""),
+            quad("_:b2", "<http://example.org/q>", "\"2\""),
+        ];
+
+        let expected = "_:c14n0 <http://example.org/p> _:c14n1 .\n\
+                         _:c14n0 <http://example.org/p> _:c14n2 .\n\
+                         _:c14n1 <http://example.org/q> \"1\" .\n\
+                         _:c14n2 <http://example.org/q> \"2\" .\n";
+
+        assert_eq!(canonicalize(&quads), expected);
+    }
+}
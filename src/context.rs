@@ -3,22 +3,82 @@ use std::collections::HashMap;
 use serde_json::{Map, Value};
 use url::Url;
 
-use super::{JsonLdError, JsonLdOptions};
+use super::{JsonLdError, JsonLdOptions, RemoteDocument};
+
+// https://www.w3.org/TR/json-ld-api/#context-processing-algorithm
+// caps the number of `@context` entries that may be chained through string
+// (remote) references, guarding against recursive-context-inclusion loops.
+const MAX_REMOTE_CONTEXTS: usize = 32;
 
 #[derive(Clone)]
 pub struct Context {
     pub base: Option<Url>,
     pub vocab: Option<String>,
     pub terms: HashMap<String, Term>,
+    pub document_loader: Option<fn(String) -> Result<RemoteDocument, JsonLdError>>,
+    pub default_language: Option<String>,
+    pub base_direction: Option<Direction>,
+    // The active context as it was before this `process` call applied any of
+    // its local contexts, kept around so a term-scoped context created with
+    // `@propagate: false` can be undone once that term's value has been
+    // expanded.
+    pub previous_context: Option<Box<Context>>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Term {
     pub iri_mapping: String,
     pub reverse: bool,
     pub type_mapping: Option<String>,
-    pub language_mapping: Option<String>,
-    pub container_mapping: Option<String>,
+    // `None` means "unset" (inherit the active context's `@language`);
+    // `Some(LanguageMapping::Null)` means an explicit `@language: null`,
+    // which clears the language rather than inheriting it -- the same
+    // `None`/`Some(Null)`/`Some(value)` tri-state as `direction_mapping`.
+    pub language_mapping: Option<LanguageMapping>,
+    pub direction_mapping: Option<Direction>,
+    // JSON-LD 1.1 containers may combine several keywords (e.g. `["@set",
+    // "@index"]`), so this is a set rather than the single 1.0-era value.
+    pub container_mapping: Vec<String>,
+    pub protected: bool,
+    pub prefix: bool,
+    pub nest: Option<String>,
+    pub index: Option<String>,
+    // Raw (unprocessed) scoped `@context`, applied lazily when a node using
+    // this term is expanded.
+    pub local_context: Option<Value>,
+}
+
+// https://www.w3.org/TR/json-ld/#base-direction
+// `Null` is a real, explicit state (not just "unset"): it's how a term or
+// nested context clears a base direction it would otherwise inherit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+    Null,
+}
+
+impl std::str::FromStr for Direction {
+    type Err = JsonLdError;
+
+    fn from_str(s: &str) -> Result<Direction, JsonLdError> {
+        match s {
+            "ltr" => Ok(Direction::Ltr),
+            "rtl" => Ok(Direction::Rtl),
+            _ => Err(JsonLdError::InvalidBaseDirection),
+        }
+    }
+}
+
+// https://www.w3.org/TR/json-ld-api/#create-term-definition, step 24:
+// `@language: null` on a term is a real, explicit state -- it clears the
+// language a value would otherwise inherit from the active context's
+// `@language`, which is distinct from the term simply not mentioning
+// `@language` at all (handled by `Term.language_mapping` being `None`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LanguageMapping {
+    Null,
+    Language(String),
 }
 
 impl Context {
@@ -27,6 +87,10 @@ impl Context {
             base: None,
             vocab: None,
             terms: HashMap::new(),
+            document_loader: None,
+            default_language: None,
+            base_direction: None,
+            previous_context: None,
         }
     }
 
@@ -35,6 +99,10 @@ impl Context {
             base,
             vocab: None,
             terms: HashMap::new(),
+            document_loader: None,
+            default_language: None,
+            base_direction: None,
+            previous_context: None,
         }
     }
 
@@ -50,6 +118,10 @@ impl Context {
             base,
             vocab: None,
             terms: HashMap::new(),
+            document_loader: Some(options.document_loader),
+            default_language: None,
+            base_direction: None,
+            previous_context: None,
         })
     }
 
@@ -59,6 +131,10 @@ impl Context {
         local_context: Value,
         remote_contexts: Vec<String>,
     ) -> Result<Context, JsonLdError> {
+        // the active context as it stood before any entry of this call's
+        // local context was applied; restored for `@propagate: false` terms
+        let original_context = self.clone();
+
         // 4
         let local_context = match local_context {
             Value::Array(a) => a,
@@ -70,17 +146,61 @@ impl Context {
             match context {
                 // 5.1
                 Value::Null => {
+                    let document_loader = self.document_loader;
                     self = Context::from_base(self.base.clone());
+                    self.document_loader = document_loader;
                 }
 
                 // 5.2
                 Value::String(s) => {
-                    // TODO: dereference context
-                    unimplemented!();
+                    self = self.process_remote_context(&s, &remote_contexts)?;
                 }
 
                 // 5.4
                 Value::Object(map) => {
+                    // 5.5: @version is only meaningful for JSON-LD 1.1 processing
+                    if let Some(version) = map.get("@version") {
+                        if version.as_f64() != Some(1.1) {
+                            return Err(JsonLdError::InvalidContextEntry);
+                        }
+                    }
+
+                    // 5.6: @import merges in a remote context as if its entries
+                    // had been written inline, before the rest of this object
+                    // is processed. It's fetched through the same depth/cycle
+                    // guard as a string (remote) context entry, not a
+                    // separate unchecked path.
+                    if let Some(import) = map.get("@import") {
+                        let iri = match import {
+                            Value::String(s) => s.clone(),
+                            _ => return Err(JsonLdError::InvalidContextEntry),
+                        };
+
+                        let (imported_context, new_remote_contexts) =
+                            self.load_remote_context(&iri, &remote_contexts)?;
+
+                        match &imported_context {
+                            Value::Object(m) if m.contains_key("@import") => {
+                                return Err(JsonLdError::InvalidContextEntry)
+                            }
+                            Value::Object(_) => {}
+                            _ => return Err(JsonLdError::InvalidRemoteContext),
+                        }
+
+                        self = self.process(imported_context, new_remote_contexts)?;
+                    }
+
+                    // 5.10: @propagate controls whether term definitions from
+                    // this context should leak past the node that scoped it.
+                    let propagate = match map.get("@propagate") {
+                        Some(Value::Bool(b)) => *b,
+                        Some(_) => return Err(JsonLdError::InvalidContextEntry),
+                        None => true,
+                    };
+                    if !propagate && self.previous_context.is_none() {
+                        self.previous_context = Some(Box::new(original_context.clone()));
+                    }
+
                     // 5.7
                     let base = map.get("@base");
                     if base.is_some() && remote_contexts.is_empty() {
@@ -135,25 +255,60 @@ impl Context {
                     if let Some(value) = map.get("@language") {
                         match value {
                             // 5.9.2
-                            Value::Null => self.vocab = None,
+                            Value::Null => self.default_language = None,
 
                             // 5.9.3
-                            Value::String(s) => self.vocab = Some(s.to_lowercase()),
+                            Value::String(s) => self.default_language = Some(s.to_lowercase()),
 
                             _ => return Err(JsonLdError::InvalidDefaultLanguage),
                         }
                     }
 
+                    // 5.10: @direction sets the base direction inherited by
+                    // string values that don't carry their own; `null`
+                    // explicitly clears whatever the active context had.
+                    if let Some(value) = map.get("@direction") {
+                        match value {
+                            Value::Null => self.base_direction = None,
+                            Value::String(s) => {
+                                self.base_direction = Some(s.parse::<Direction>()?)
+                            }
+                            _ => return Err(JsonLdError::InvalidBaseDirection),
+                        }
+                    }
+
+                    // all terms defined by this context object default to
+                    // `@protected` when the object itself set `@protected: true`
+                    let context_protected = matches!(map.get("@protected"), Some(Value::Bool(true)));
+
                     // 5.11
                     let mut defined: HashMap<String, bool> = HashMap::new();
 
                     // 5.12
                     for term in map.keys() {
-                        if term == "@base" || term == "@vocab" || term == "@language" {
+                        if term == "@base"
+                            || term == "@vocab"
+                            || term == "@language"
+                            || term == "@direction"
+                            || term == "@version"
+                            || term == "@import"
+                            || term == "@propagate"
+                            || term == "@protected"
+                        {
                             continue;
                         };
 
                         self.create_term_definition(&map, term, &mut defined)?;
+
+                        let explicit_protected = matches!(
+                            map.get(term),
+                            Some(Value::Object(d)) if d.contains_key("@protected")
+                        );
+                        if context_protected && !explicit_protected {
+                            if let Some(t) = self.terms.get_mut(term) {
+                                t.protected = true;
+                            }
+                        }
                     }
                 }
 
@@ -165,6 +320,71 @@ impl Context {
         Ok(self)
     }
 
+    // 5.2: `context` is a string entry referencing a remote context document.
+    // Dereference it through the configured document loader, pull out its
+    // top-level `@context`, and recursively process that into the current
+    // active context (not a fresh one -- terms/vocab already accumulated in
+    // `self` must survive, and the document's own base is untouched),
+    // tracking `remote_contexts` so cyclic/too-deep inclusion is rejected
+    // rather than recursing forever.
+    fn process_remote_context(
+        self,
+        reference: &str,
+        remote_contexts: &[String],
+    ) -> Result<Context, JsonLdError> {
+        let (context_value, new_remote_contexts) =
+            self.load_remote_context(reference, remote_contexts)?;
+
+        self.process(context_value, new_remote_contexts)
+    }
+
+    // Resolves `reference` against the active base (if relative), guards it
+    // against `MAX_REMOTE_CONTEXTS`/cyclic inclusion, dereferences it through
+    // the document loader, and returns its `@context` value together with
+    // `remote_contexts` extended to include it -- shared by both a string
+    // (remote) context entry and an `@import`, so neither can bypass the
+    // other's depth/cycle accounting.
+    fn load_remote_context(
+        &self,
+        reference: &str,
+        remote_contexts: &[String],
+    ) -> Result<(Value, Vec<String>), JsonLdError> {
+        let context_iri = if is_absolute_iri(reference) {
+            reference.to_owned()
+        } else if is_relative_iri(reference) {
+            self.base
+                .as_ref()
+                .ok_or(JsonLdError::LoadingRemoteContextFailed)?
+                .join(reference)
+                .or(Err(JsonLdError::LoadingRemoteContextFailed))?
+                .to_string()
+        } else {
+            return Err(JsonLdError::LoadingRemoteContextFailed);
+        };
+
+        if remote_contexts.len() >= MAX_REMOTE_CONTEXTS || remote_contexts.contains(&context_iri) {
+            return Err(JsonLdError::ContextOverflow);
+        }
+
+        let document_loader = self
+            .document_loader
+            .ok_or(JsonLdError::LoadingRemoteContextFailed)?;
+
+        let remote_document = document_loader(context_iri.clone())?;
+
+        let context_value = match remote_document.document {
+            Value::Object(mut map) => map
+                .remove("@context")
+                .ok_or(JsonLdError::InvalidRemoteContext)?,
+            _ => return Err(JsonLdError::InvalidRemoteContext),
+        };
+
+        let mut new_remote_contexts = remote_contexts.to_vec();
+        new_remote_contexts.push(context_iri);
+
+        Ok((context_value, new_remote_contexts))
+    }
+
     fn create_term_definition(
         &mut self,
         local_context: &Map<String, Value>,
@@ -190,6 +410,12 @@ impl Context {
             return Err(JsonLdError::KeywordRedefinition);
         }
 
+        // 3: a term already marked `@protected` by an earlier context layer
+        // may only be "redefined" if the new definition is identical to the
+        // one already in effect -- checked once the new definition has been
+        // fully built, below.
+        let previous_definition = self.terms.get(term).cloned();
+
         // 7
         self.terms.remove(term);
 
@@ -211,7 +437,11 @@ impl Context {
                 let mut definition_reverse: bool;
                 let mut definition_type_mapping = None;
                 let mut definition_language_mapping = None;
-                let mut definition_container_mapping = None;
+                let mut definition_direction_mapping = None;
+                let mut definition_container_mapping: Vec<String> = Vec::new();
+                let mut definition_nest = None;
+                let mut definition_index = None;
+                let mut definition_local_context = None;
 
                 // 13
                 if let Some(t) = value.get("@type") {
@@ -252,32 +482,40 @@ impl Context {
                         // 14.4
                         if let Some(container) = value.get("@container") {
                             definition_container_mapping = match container {
-                                Value::Null => None,
-                                Value::String(s) => {
-                                    if s == "@set" || s == "@index" {
-                                        Some(s.to_owned())
-                                    } else {
-                                        return Err(JsonLdError::InvalidReverseProperty);
-                                    }
+                                Value::Null => Vec::new(),
+                                Value::String(s) if s == "@set" || s == "@index" => {
+                                    vec![s.to_owned()]
                                 }
                                 _ => return Err(JsonLdError::InvalidReverseProperty),
                             };
                         }
 
+                        let definition_protected = match value.get("@protected") {
+                            Some(Value::Bool(b)) => *b,
+                            Some(_) => return Err(JsonLdError::InvalidTermDefinition),
+                            None => false,
+                        };
+
                         // 14.5
                         definition_reverse = true;
 
                         // 14.6
-                        self.terms.insert(
-                            term.to_string(),
-                            Term {
-                                iri_mapping: definition_iri_mapping,
-                                reverse: definition_reverse,
-                                type_mapping: definition_type_mapping,
-                                language_mapping: definition_language_mapping,
-                                container_mapping: definition_container_mapping,
-                            },
-                        );
+                        let definition = Term {
+                            iri_mapping: definition_iri_mapping,
+                            reverse: definition_reverse,
+                            type_mapping: definition_type_mapping,
+                            language_mapping: definition_language_mapping,
+                            direction_mapping: None,
+                            container_mapping: definition_container_mapping,
+                            protected: definition_protected,
+                            prefix: false,
+                            nest: None,
+                            index: None,
+                            local_context: None,
+                        };
+
+                        reject_unless_identical_redefinition(&previous_definition, &definition)?;
+                        self.terms.insert(term.to_string(), definition);
 
                         defined.insert(term.to_string(), true);
 
@@ -291,7 +529,7 @@ impl Context {
                 definition_reverse = false;
 
                 // 16
-                if let Some(id) = value.get("id") {
+                if let Some(id) = value.get("@id") {
                     match id {
                         Value::String(s) => {
                             if s != term {
@@ -302,6 +540,8 @@ impl Context {
                                 }
 
                                 definition_iri_mapping = id;
+                            } else {
+                                definition_iri_mapping = term.to_owned();
                             }
                         }
                         // 16.2
@@ -333,10 +573,105 @@ impl Context {
                         None => return Err(JsonLdError::InvalidIRIMapping),
                     }
                 }
+
+                // 20
+                if let Some(container) = value.get("@container") {
+                    definition_container_mapping = parse_container_mapping(container)?;
+                }
+
+                // 21: a term can be used as a CURIE prefix either because it
+                // says so explicitly, or because its IRI mapping already ends
+                // in a gen-delim character (e.g. `.../ns#`) and the term
+                // itself isn't already a CURIE.
+                let definition_prefix = match value.get("@prefix") {
+                    Some(Value::Bool(b)) => {
+                        if term.contains(':') {
+                            return Err(JsonLdError::InvalidTermDefinition);
+                        }
+                        *b
+                    }
+                    Some(_) => return Err(JsonLdError::InvalidTermDefinition),
+                    None => !term.contains(':') && ends_with_gen_delim(&definition_iri_mapping),
+                };
+
+                // 22
+                if let Some(index) = value.get("@index") {
+                    match index {
+                        Value::String(s) => {
+                            let expanded = self.expand_iri(s, false, true, local_context, defined)?;
+                            if !is_absolute_iri(&expanded) {
+                                return Err(JsonLdError::InvalidTermDefinition);
+                            }
+                            definition_index = Some(s.clone());
+                        }
+                        _ => return Err(JsonLdError::InvalidTermDefinition),
+                    }
+                }
+
+                // 23: the scoped context is kept raw and only applied once a
+                // node using this term is expanded.
+                if let Some(scoped_context) = value.get("@context") {
+                    definition_local_context = Some(scoped_context.clone());
+                }
+
+                // 24
+                if let Some(language) = value.get("@language") {
+                    definition_language_mapping = match language {
+                        Value::Null => Some(LanguageMapping::Null),
+                        Value::String(s) => Some(LanguageMapping::Language(s.to_lowercase())),
+                        _ => return Err(JsonLdError::InvalidLanguageMapping),
+                    };
+                }
+
+                // 25: unlike the active context's base direction, a term's
+                // `null` is a real value -- it means "this term has no
+                // direction", not "inherit whatever the context has".
+                if let Some(direction) = value.get("@direction") {
+                    definition_direction_mapping = match direction {
+                        Value::Null => Some(Direction::Null),
+                        Value::String(s) => Some(s.parse::<Direction>()?),
+                        _ => return Err(JsonLdError::InvalidBaseDirection),
+                    };
+                }
+
+                // 26
+                if let Some(nest) = value.get("@nest") {
+                    match nest {
+                        Value::String(s) if s == "@nest" || !is_keyword(s) => {
+                            definition_nest = Some(s.clone())
+                        }
+                        _ => return Err(JsonLdError::InvalidNestValue),
+                    }
+                }
+
+                let definition_protected = match value.get("@protected") {
+                    Some(Value::Bool(b)) => *b,
+                    Some(_) => return Err(JsonLdError::InvalidTermDefinition),
+                    None => false,
+                };
+
+                let definition = Term {
+                    iri_mapping: definition_iri_mapping,
+                    reverse: definition_reverse,
+                    type_mapping: definition_type_mapping,
+                    language_mapping: definition_language_mapping,
+                    direction_mapping: definition_direction_mapping,
+                    container_mapping: definition_container_mapping,
+                    protected: definition_protected,
+                    prefix: definition_prefix,
+                    nest: definition_nest,
+                    index: definition_index,
+                    local_context: definition_local_context,
+                };
+
+                reject_unless_identical_redefinition(&previous_definition, &definition)?;
+                self.terms.insert(term.to_string(), definition);
             }
             _ => return Err(JsonLdError::InvalidTermDefinition),
         }
 
+        defined.insert(term.to_string(), true);
+
         Ok(())
     }
 
@@ -371,7 +706,11 @@ impl Context {
             // 5.1
             let (prefix, suffix) = value.split_at(i);
 
-            // 5.2
+            // 5.2: blank node identifiers, and any value whose suffix starts
+            // with `//` (i.e. it has an authority component, like
+            // `http://...`), are already absolute IRIs and are returned
+            // untouched rather than treated as a `prefix:suffix` CURIE.
+            let suffix = &suffix[1..];
             if prefix == "_" || suffix.starts_with("//") {
                 return Ok(value.to_string());
             }
@@ -402,20 +741,146 @@ impl Context {
         // 7
         Ok(value.to_string())
     }
+
+    // A read-only variant of `expand_iri` for use once the active context is
+    // already fully built (e.g. during expansion), where there's no local
+    // context left to lazily define terms from.
+    pub(crate) fn expand_iri_value(&self, value: &str) -> String {
+        if is_keyword(value) {
+            return value.to_string();
+        }
+
+        if let Some(term) = self.terms.get(value) {
+            return term.iri_mapping.clone();
+        }
+
+        if let Some(i) = value.find(':') {
+            let (prefix, suffix) = (&value[..i], &value[i + 1..]);
+            if prefix == "_" || suffix.starts_with("//") {
+                return value.to_string();
+            }
+            if let Some(term) = self.terms.get(prefix) {
+                return term.iri_mapping.clone() + suffix;
+            }
+            return value.to_string();
+        }
+
+        match &self.vocab {
+            Some(vocab) => vocab.to_owned() + value,
+            None => value.to_string(),
+        }
+    }
+}
+
+// https://w3c.github.io/json-ld-api/#create-term-definition, step 3's note:
+// a term already marked `@protected` can still be "redefined" so long as the
+// new definition is identical to the one already in effect (its `protected`
+// flag aside, since a context-level `@protected` is applied only after this
+// check) -- otherwise it's a hard error.
+fn reject_unless_identical_redefinition(
+    previous: &Option<Term>,
+    candidate: &Term,
+) -> Result<(), JsonLdError> {
+    match previous {
+        Some(previous)
+            if previous.protected
+                && *previous != (Term { protected: true, ..candidate.clone() }) =>
+        {
+            Err(JsonLdError::ProtectedTermRedefinition)
+        }
+        _ => Ok(()),
+    }
 }
 
 fn is_keyword(val: &str) -> bool {
     return match val {
-        "@container" | "@context" | "@graph" | "@id" | "@index" | "@language" | "@list"
-        | "@reverse" | "@set" | "@type" | "@value" | "@vocab" => true,
+        "@base" | "@container" | "@context" | "@direction" | "@graph" | "@id" | "@import"
+        | "@index" | "@language" | "@list" | "@nest" | "@prefix" | "@propagate" | "@protected"
+        | "@reverse" | "@set" | "@type" | "@value" | "@version" | "@vocab" => true,
         _ => false,
     };
 }
 
+// https://w3c.github.io/json-ld-api/#create-term-definition, step 20: a
+// `@container` value may be a single keyword or a set of them.
+fn parse_container_mapping(container: &Value) -> Result<Vec<String>, JsonLdError> {
+    const ALLOWED: [&str; 7] = [
+        "@list", "@set", "@index", "@language", "@id", "@type", "@graph",
+    ];
+
+    let values = match container {
+        Value::Null => return Ok(Vec::new()),
+        Value::String(s) => vec![s.clone()],
+        Value::Array(items) => items
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => Ok(s.clone()),
+                _ => Err(JsonLdError::InvalidContainerMapping),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err(JsonLdError::InvalidContainerMapping),
+    };
+
+    if !values.iter().all(|v| ALLOWED.contains(&v.as_str())) {
+        return Err(JsonLdError::InvalidContainerMapping);
+    }
+
+    // `@list` can't be combined with anything else
+    if values.contains(&"@list".to_string()) && values.len() > 1 {
+        return Err(JsonLdError::InvalidContainerMapping);
+    }
+
+    // canonicalize order so two definitions naming the same set of keywords
+    // in a different order compare equal (e.g. for protected-term redefinition)
+    let mut values = values;
+    values.sort();
+    Ok(values)
+}
+
+fn ends_with_gen_delim(iri: &str) -> bool {
+    matches!(
+        iri.chars().last(),
+        Some(':' | '/' | '?' | '#' | '[' | ']' | '@')
+    )
+}
+
+// https://www.rfc-editor.org/rfc/rfc3987#section-2.2 (scheme)
+// ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
+fn is_valid_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
+
+// https://www.rfc-editor.org/rfc/rfc3987#section-2.2 (IRI = scheme ":" ihier-part [ "?" iquery ] [ "#" ifragment ])
+// `Url::parse` already accepts non-ASCII code points in the path/query (it
+// percent-encodes them internally), so it doubles as an IRI parser here as
+// long as we gate it on a syntactically valid scheme first -- blank node
+// identifiers (`_:b0`) use the same `prefix:suffix` shape but are never
+// absolute IRIs.
 fn is_absolute_iri(iri: &str) -> bool {
-    unimplemented!();
+    if iri.starts_with("_:") {
+        return false;
+    }
+
+    match iri.find(':') {
+        Some(i) if is_valid_scheme(&iri[..i]) => Url::parse(iri).is_ok(),
+        _ => false,
+    }
 }
 
+// https://www.rfc-editor.org/rfc/rfc3987#section-2.2 (irelative-ref)
+// A relative reference is anything that isn't itself an absolute IRI, a
+// blank node identifier, or a string containing whitespace/control
+// characters (which can't appear in an IRI reference at all).
 fn is_relative_iri(iri: &str) -> bool {
-    unimplemented!();
+    if iri.starts_with("_:") || is_absolute_iri(iri) {
+        return false;
+    }
+
+    !iri.chars().any(|c| c.is_whitespace() || c.is_control())
 }
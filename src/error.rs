@@ -0,0 +1,25 @@
+// https://www.w3.org/TR/json-ld-api/#jsonlderrorcode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonLdError {
+    ContextOverflow,
+    CyclicIRIMapping,
+    InvalidBaseDirection,
+    InvalidBaseIRI,
+    InvalidContainerMapping,
+    InvalidContextEntry,
+    InvalidDefaultLanguage,
+    InvalidIRIMapping,
+    InvalidLanguageMapping,
+    InvalidLocalContext,
+    InvalidNestValue,
+    InvalidRemoteContext,
+    InvalidReverseProperty,
+    InvalidTermDefinition,
+    InvalidTypeMapping,
+    InvalidVocabMapping,
+    KeywordRedefinition,
+    LoadingDocumentFailed,
+    LoadingRemoteContextFailed,
+    MultipleContextLinkHeaders,
+    ProtectedTermRedefinition,
+}
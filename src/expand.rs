@@ -0,0 +1,255 @@
+use serde_json::{Map, Value};
+
+use super::context::{Direction, LanguageMapping};
+use super::{Context, JsonLdError, JsonLdOptions};
+
+// https://www.w3.org/TR/json-ld-api/#expansion-algorithm
+//
+// A document's own top-level `@context` (handled like any other node
+// object's scoped context, in `expand_node_object`) is what actually
+// initializes the active context here; `options.expand_context` names an
+// additional context to layer in first. TODO: that option is an IRI/path
+// reference rather than an inline value, so applying it needs a
+// `document_loader` round-trip this function doesn't yet make.
+pub fn expand(document: Value, options: JsonLdOptions) -> Result<Value, JsonLdError> {
+    let context = Context::from_options(options)?;
+    let expanded = expand_element(&context, None, &document)?;
+
+    // step 9: a bare top-level value is always wrapped in an array
+    Ok(match expanded {
+        Value::Array(_) => expanded,
+        Value::Null => Value::Array(Vec::new()),
+        other => Value::Array(vec![other]),
+    })
+}
+
+fn expand_element(
+    context: &Context,
+    active_property: Option<&str>,
+    element: &Value,
+) -> Result<Value, JsonLdError> {
+    match element {
+        Value::Null => Ok(Value::Null),
+
+        Value::Array(items) => {
+            let mut result = Vec::new();
+            for item in items {
+                match expand_element(context, active_property, item)? {
+                    Value::Null => {}
+                    Value::Array(nested) => result.extend(nested),
+                    other => result.push(other),
+                }
+            }
+            Ok(Value::Array(result))
+        }
+
+        Value::Object(map) => expand_node_object(context, map),
+
+        // a bare scalar is expanded relative to the active property's term
+        scalar => expand_value(context, active_property, scalar, None),
+    }
+}
+
+fn expand_node_object(
+    context: &Context,
+    map: &Map<String, Value>,
+) -> Result<Value, JsonLdError> {
+    // a node object scoped by a local `@context` gets its own active context
+    let context = match map.get("@context") {
+        Some(local_context) => context.clone().process(local_context.clone(), Vec::new())?,
+        None => context.clone(),
+    };
+
+    // `@propagate: false` on that context means node objects nested inside
+    // this one's properties -- but not this node object itself, which is
+    // exactly what the context scopes -- must not see it; they see whatever
+    // was active immediately before it was applied instead.
+    let nested_context = match &context.previous_context {
+        Some(previous) => previous.as_ref().clone(),
+        None => context.clone(),
+    };
+
+    let mut result = Map::new();
+
+    for (key, value) in map {
+        if key == "@context" {
+            continue;
+        }
+
+        if key == "@value" || key == "@id" || key == "@type" || key == "@language"
+            || key == "@direction"
+        {
+            result.insert(key.clone(), value.clone());
+            continue;
+        }
+
+        let term = context.terms.get(key);
+        let expanded_property = term.map(|t| t.iri_mapping.clone()).unwrap_or_else(|| key.clone());
+        let has_own_scope = term.and_then(|t| t.local_context.as_ref()).is_some();
+
+        // a term's own scoped `@context` applies only while expanding that
+        // term's value, not to the rest of the node object
+        let value_context = match term.and_then(|t| t.local_context.clone()) {
+            Some(scoped_context) => context.clone().process(scoped_context, Vec::new())?,
+            None => context.clone(),
+        };
+        let term = value_context.terms.get(key);
+
+        // a node object directly nested in this property's value is exactly
+        // what a term's own scoped context (if any) targets, so it always
+        // sees `value_context`; absent that, it's "contained within" this
+        // node object, so `@propagate: false` on `context` applies to it too
+        let recursion_context = if has_own_scope { &value_context } else { &nested_context };
+
+        // language-map containers turn `{"en": "Hello", "fr": ["Bonjour"]}`
+        // into a set of `@value`/`@language` (/`@direction`) objects
+        let is_language_container = term
+            .map(|t| t.container_mapping.iter().any(|c| c == "@language"))
+            .unwrap_or(false);
+
+        let expanded_value = if is_language_container {
+            expand_language_map(&value_context, term, value)?
+        } else {
+            expand_element_for_term(&value_context, recursion_context, term, Some(&expanded_property), value)?
+        };
+
+        result.insert(expanded_property, expanded_value);
+    }
+
+    Ok(Value::Object(result))
+}
+
+fn expand_element_for_term(
+    context: &Context,
+    child_context: &Context,
+    term: Option<&super::context::Term>,
+    active_property: Option<&str>,
+    value: &Value,
+) -> Result<Value, JsonLdError> {
+    match value {
+        Value::Array(items) => {
+            let mut expanded = Vec::new();
+            for item in items {
+                expanded.push(expand_element_for_term(context, child_context, term, active_property, item)?);
+            }
+            Ok(Value::Array(expanded))
+        }
+        Value::Object(_) => expand_element(child_context, active_property, value),
+        scalar => expand_value(context, active_property, scalar, term),
+    }
+}
+
+// https://www.w3.org/TR/json-ld-api/#value-expansion
+fn expand_value(
+    context: &Context,
+    active_property: Option<&str>,
+    value: &Value,
+    term: Option<&super::context::Term>,
+) -> Result<Value, JsonLdError> {
+    let term = term.or_else(|| active_property.and_then(|p| context.terms.get(p)));
+
+    if let Some(t) = term {
+        if let Some(type_mapping) = &t.type_mapping {
+            if type_mapping == "@id" || type_mapping == "@vocab" {
+                if let Value::String(s) = value {
+                    let mut result = Map::new();
+                    result.insert("@id".to_string(), Value::String(context.expand_iri_value(s)));
+                    return Ok(Value::Object(result));
+                }
+            } else {
+                // any other type mapping is a datatype IRI: the value is
+                // tagged with it via `@type` and, unlike the untyped case
+                // below, never gets a `@language`/`@direction`.
+                let mut result = Map::new();
+                result.insert("@value".to_string(), value.clone());
+                result.insert("@type".to_string(), Value::String(type_mapping.clone()));
+                return Ok(Value::Object(result));
+            }
+        }
+    }
+
+    let mut result = Map::new();
+    result.insert("@value".to_string(), value.clone());
+
+    if let Value::String(_) = value {
+        let language = match term.and_then(|t| t.language_mapping.clone()) {
+            Some(LanguageMapping::Null) => None,
+            Some(LanguageMapping::Language(language)) => Some(language),
+            None => context.default_language.clone(),
+        };
+        if let Some(language) = language {
+            result.insert("@language".to_string(), Value::String(language));
+        }
+
+        let direction = match term.and_then(|t| t.direction_mapping) {
+            Some(Direction::Null) => None,
+            Some(explicit) => Some(explicit),
+            None => context.base_direction,
+        };
+        if let Some(direction) = direction {
+            let s = match direction {
+                Direction::Ltr => "ltr",
+                Direction::Rtl => "rtl",
+                Direction::Null => unreachable!("cleared above"),
+            };
+            result.insert("@direction".to_string(), Value::String(s.to_string()));
+        }
+    }
+
+    Ok(Value::Object(result))
+}
+
+// https://www.w3.org/TR/json-ld-api/#expansion-algorithm, language-map branch:
+// `@container: @language` terms store their value as a map of
+// language-tag to string (or array of strings), which expands into one
+// `@value`/`@language` object per tag/string pair.
+fn expand_language_map(
+    context: &Context,
+    term: Option<&super::context::Term>,
+    value: &Value,
+) -> Result<Value, JsonLdError> {
+    let map = match value {
+        Value::Object(m) => m,
+        _ => return Err(JsonLdError::InvalidLanguageMapping),
+    };
+
+    let direction = match term.and_then(|t| t.direction_mapping) {
+        Some(Direction::Null) => None,
+        Some(explicit) => Some(explicit),
+        None => context.base_direction,
+    };
+
+    let mut result = Vec::new();
+    for (language, values) in map {
+        let values = match values {
+            Value::Array(items) => items.clone(),
+            other => vec![other.clone()],
+        };
+
+        for item in values {
+            let text = match item {
+                Value::String(s) => s,
+                Value::Null => continue,
+                _ => return Err(JsonLdError::InvalidLanguageMapping),
+            };
+
+            let mut object = Map::new();
+            object.insert("@value".to_string(), Value::String(text));
+            if language != "@none" {
+                object.insert("@language".to_string(), Value::String(language.to_lowercase()));
+            }
+            if let Some(direction) = direction {
+                let s = match direction {
+                    Direction::Ltr => "ltr",
+                    Direction::Rtl => "rtl",
+                    Direction::Null => unreachable!("cleared above"),
+                };
+                object.insert("@direction".to_string(), Value::String(s.to_string()));
+            }
+
+            result.push(Value::Object(object));
+        }
+    }
+
+    Ok(Value::Array(result))
+}
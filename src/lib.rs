@@ -1,3 +1,4 @@
+mod canonicalize;
 mod compact;
 mod context;
 mod error;
@@ -8,6 +9,7 @@ mod options;
 mod remote_document;
 mod to_rdf;
 
+pub use canonicalize::{canonicalize, Quad};
 pub use compact::compact;
 pub use context::Context;
 pub use error::JsonLdError;
@@ -1,3 +1,4 @@
+use super::remote_document::default_document_loader;
 use super::JsonLdError;
 use super::RemoteDocument;
 
@@ -9,3 +10,15 @@ pub struct JsonLdOptions {
     pub expand_context: Option<String>,
     pub processing_mode: Option<String>,
 }
+
+impl Default for JsonLdOptions {
+    fn default() -> Self {
+        JsonLdOptions {
+            base: None,
+            compact_arrays: true,
+            document_loader: default_document_loader,
+            expand_context: None,
+            processing_mode: None,
+        }
+    }
+}
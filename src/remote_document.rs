@@ -1,8 +1,95 @@
 use serde_json::Value;
 
+use super::JsonLdError;
+
 // https://www.w3.org/TR/json-ld-api/#idl-def-RemoteDocument
 pub struct RemoteDocument {
     pub document: Value,
     pub document_url: String,
-    pub context_url: String,
+    pub context_url: Option<String>,
+}
+
+const LINK_REL_CONTEXT: &str = "http://www.w3.org/ns/json-ld#context";
+
+// https://www.w3.org/TR/json-ld-api/#idl-def-LoadDocumentOptions
+// The default loader performs standard JSON-LD content negotiation: it asks
+// for `application/ld+json` first, falls back to `application/json`, and
+// also accepts `application/activity+json` (treated as plain JSON-LD, as
+// ActivityPub documents commonly are). When the response isn't already
+// JSON-LD (per its `profile` media type parameter) and carries a context
+// `Link` header, that header's target becomes `context_url` so the caller
+// can merge in the referenced context.
+pub fn default_document_loader(url: String) -> Result<RemoteDocument, JsonLdError> {
+    let response = ureq::get(&url)
+        .set(
+            "Accept",
+            "application/ld+json, application/activity+json, application/json",
+        )
+        .call()
+        .or(Err(JsonLdError::LoadingDocumentFailed))?;
+
+    let document_url = response.get_url().to_owned();
+
+    let content_type = response
+        .header("Content-Type")
+        .unwrap_or("application/octet-stream")
+        .to_owned();
+
+    let context_url = if is_jsonld_content_type(&content_type) {
+        None
+    } else {
+        context_link_header(response.header("Link"))?
+    };
+
+    let document: Value = response
+        .into_json()
+        .or(Err(JsonLdError::LoadingDocumentFailed))?;
+
+    Ok(RemoteDocument {
+        document,
+        document_url,
+        context_url,
+    })
+}
+
+// `application/ld+json` and `application/activity+json` (ActivityPub's
+// content type, which is JSON-LD under the hood) are always JSON-LD.
+// `application/json` only counts if it isn't flagged with a `profile`.
+fn is_jsonld_content_type(content_type: &str) -> bool {
+    let (media_type, params) = match content_type.split_once(';') {
+        Some((m, p)) => (m.trim(), p),
+        None => (content_type.trim(), ""),
+    };
+
+    match media_type {
+        "application/ld+json" | "application/activity+json" => true,
+        "application/json" => !params
+            .split(';')
+            .filter_map(|p| p.trim().strip_prefix("profile="))
+            .any(|profile| !profile.trim_matches('"').is_empty()),
+        _ => false,
+    }
+}
+
+fn context_link_header(header: Option<&str>) -> Result<Option<String>, JsonLdError> {
+    let header = match header {
+        Some(h) => h,
+        None => return Ok(None),
+    };
+
+    let mut context_links = header
+        .split(',')
+        .filter(|link| link.contains(&format!("rel=\"{}\"", LINK_REL_CONTEXT)))
+        .filter_map(|link| {
+            let start = link.find('<')?;
+            let end = link.find('>')?;
+            Some(link[start + 1..end].to_owned())
+        });
+
+    let first = context_links.next();
+    if context_links.next().is_some() {
+        return Err(JsonLdError::MultipleContextLinkHeaders);
+    }
+
+    Ok(first)
 }